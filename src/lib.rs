@@ -1,11 +1,26 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use swc_common::comments::{Comments, SingleThreadedComments};
 use swc_common::BytePos;
-use swc_ecma_ast::{Decl, ExportDecl};
+use swc_ecma_ast::{
+    ArrowExpr, AssignExpr, BinExpr, BinaryOp, BindingIdent, BlockStmt, BreakStmt, CallExpr, Callee,
+    CatchClause, Class, ClassMember, CondExpr, Constructor, ContinueStmt, Decl, Decorator,
+    ExportAll, ExportDecl, ExportNamedSpecifier, ExportSpecifier, Expr, ExprStmt, FnExpr, ForHead,
+    ForInStmt, ForOfStmt, ForStmt, Function, GetterProp, Ident, IfStmt, ImportDecl,
+    ImportSpecifier, JSXAttrName, JSXMemberExpr, LabeledStmt, Lit, MemberExpr, MemberProp,
+    MetaPropKind, Module, ModuleDecl, ModuleExportName, ModuleItem, NamedExport, NewExpr,
+    ParamOrTsParamProp, PrivateName, PropName, SetterProp, Stmt, SuperProp, SwitchStmt, TaggedTpl,
+    TsEnumMemberId, TsGetterSignature, TsImportEqualsDecl, TsMethodSignature, TsModuleRef,
+    TsPropertySignature, TsQualifiedName, TsSetterSignature, TsTypeParam, UnaryExpr, UnaryOp,
+    UpdateExpr,
+};
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
 use swc_ecma_visit::{Visit, VisitWith};
 
-struct ExportedFunction {
-    name: String,
-    start_pos: usize,
+pub struct ExportedFunction {
+    pub name: String,
+    pub start_pos: usize,
 }
 struct ExportedFunctions {
     functions: Vec<ExportedFunction>,
@@ -25,15 +40,998 @@ impl Visit for ExportedFunctions {
     }
 }
 
+pub struct ReExport {
+    pub source: String,
+    pub name: String,
+}
+
+pub struct ImportBinding {
+    pub source: String,
+    // The name exported by `source`: `default` for default imports, `*` for namespace imports
+    pub name: String,
+    pub local: String,
+    pub type_only: bool,
+    // Whether `local` appears anywhere in the module outside its import statement
+    pub referenced: bool,
+}
+
+pub struct DynamicImport {
+    pub source: String,
+    // `import('./polyfill');` runs the module but consumes none of its exports
+    pub side_effect_only: bool,
+    // Inside a conditional on `process.env`/`import.meta.env`, so whether it runs depends on
+    // the environment rather than on the code
+    pub env_gated: bool,
+    pub start_pos: usize,
+}
+
+pub struct ModuleInfo {
+    pub import_bindings: Vec<ImportBinding>,
+    pub reexports: Vec<ReExport>,
+    // Sources of `export * from '...'`, which re-exports everything except `default`
+    pub wildcard_reexports: Vec<String>,
+    // Sources of `export * as ns from '...'`, which exposes the whole namespace, `default` included
+    pub namespace_reexports: Vec<String>,
+    pub dynamic_imports: Vec<DynamicImport>,
+    pub import_globs: Vec<String>,
+    pub url_references: Vec<String>,
+    pub dynamic_requires: Vec<usize>,
+    // Sources of literal `require('./x')` calls in CommonJS modules
+    pub requires: Vec<String>,
+    pub has_side_effects: bool,
+    pub commonjs: bool,
+    // Set by a `/* unused-buddy-ignore-file */` comment anywhere in the file
+    pub ignored_file: bool,
+    // 1-based lines following a `// unused-buddy-ignore-next-line` comment
+    pub ignored_lines: Vec<usize>,
+    // Emitted by a code generator, going by a marker in a header comment. Its exports shouldn't
+    // be reported, but its imports still count as uses of other modules
+    pub generated: bool,
+    // Set when the file couldn't be parsed. Nothing else is recorded then, so callers should treat
+    // the module as opaque: it may import anything and has side effects as far as we know
+    pub parse_error: Option<String>,
+}
+
+impl ModuleInfo {
+    pub fn unused_imports(&self) -> impl Iterator<Item = &ImportBinding> {
+        self.import_bindings
+            .iter()
+            .filter(|binding| !binding.referenced)
+    }
+
+    // The `(source, name)` pairs this module imports. Under `strict_usage` an import only counts
+    // once it is actually referenced, so an export kept alive by a dead import isn't used.
+    pub fn imported_symbols(&self, strict_usage: bool) -> Vec<(&str, &str)> {
+        self.import_bindings
+            .iter()
+            .filter(|binding| !strict_usage || binding.referenced)
+            .map(|binding| (binding.source.as_str(), binding.name.as_str()))
+            .collect()
+    }
+
+    pub fn is_line_ignored(&self, line: usize) -> bool {
+        self.ignored_file || self.ignored_lines.contains(&line)
+    }
+
+    // Whether this module passes `name` from `source` through to its own importers.
+    pub fn reexports_from(&self, source: &str, name: &str) -> bool {
+        self.reexports
+            .iter()
+            .any(|reexport| reexport.source == source && reexport.name == name)
+            || (name != "default" && self.wildcard_reexports.iter().any(|s| s == source))
+            || self.namespace_reexports.iter().any(|s| s == source)
+    }
+}
+
+impl Visit for ModuleInfo {
+    fn visit_import_decl(&mut self, import_decl: &ImportDecl) {
+        let source = import_decl.src.value.to_string();
+
+        for specifier in &import_decl.specifiers {
+            let (name, local, type_only) = match specifier {
+                ImportSpecifier::Named(named) => (
+                    named
+                        .imported
+                        .as_ref()
+                        .map(module_export_name)
+                        .unwrap_or_else(|| named.local.sym.to_string()),
+                    &named.local,
+                    named.is_type_only,
+                ),
+                ImportSpecifier::Default(default) => ("default".to_string(), &default.local, false),
+                ImportSpecifier::Namespace(namespace) => ("*".to_string(), &namespace.local, false),
+            };
+
+            self.import_bindings.push(ImportBinding {
+                source: source.clone(),
+                name,
+                local: local.sym.to_string(),
+                type_only: type_only || import_decl.type_only,
+                referenced: false,
+            });
+        }
+    }
+
+    // `import helper = require('./helper')` binds the whole `module.exports` of './helper', much
+    // like a namespace import
+    fn visit_ts_import_equals_decl(&mut self, import_equals: &TsImportEqualsDecl) {
+        if !self.commonjs {
+            return;
+        }
+        let TsModuleRef::TsExternalModuleRef(module_ref) = &import_equals.module_ref else {
+            return;
+        };
+
+        self.import_bindings.push(ImportBinding {
+            source: module_ref.expr.value.to_string(),
+            name: "*".to_string(),
+            local: import_equals.id.sym.to_string(),
+            type_only: import_equals.is_type_only,
+            referenced: false,
+        });
+    }
+
+    fn visit_named_export(&mut self, named_export: &NamedExport) {
+        let Some(source) = &named_export.src else {
+            return;
+        };
+
+        for specifier in &named_export.specifiers {
+            match specifier {
+                // `export { internal as publicName } from './a'` consumes `internal` from './a',
+                // so record the source-side name rather than the exported alias
+                ExportSpecifier::Named(named) => self.reexports.push(ReExport {
+                    source: source.value.to_string(),
+                    name: module_export_name(&named.orig),
+                }),
+                ExportSpecifier::Namespace(_) => {
+                    self.namespace_reexports.push(source.value.to_string())
+                }
+                ExportSpecifier::Default(_) => {}
+            }
+        }
+    }
+
+    fn visit_export_all(&mut self, export_all: &ExportAll) {
+        self.wildcard_reexports
+            .push(export_all.src.value.to_string());
+    }
+
+    fn visit_expr_stmt(&mut self, expr_stmt: &ExprStmt) {
+        // `await import(...)`, `void import(...)` and `(import(...))` all discard the module
+        let mut expr = &*expr_stmt.expr;
+        loop {
+            expr = match expr {
+                Expr::Await(await_expr) => &await_expr.arg,
+                Expr::Unary(unary_expr) if unary_expr.op == UnaryOp::Void => &unary_expr.arg,
+                Expr::Paren(paren_expr) => &paren_expr.expr,
+                _ => break,
+            };
+        }
+
+        if let Expr::Call(call_expr) = expr {
+            if let Some(source) = dynamic_import_source(call_expr) {
+                self.dynamic_imports.push(DynamicImport {
+                    source,
+                    side_effect_only: true,
+                    env_gated: false,
+                    start_pos: call_expr.span.lo().0 as usize,
+                });
+                call_expr.args.visit_with(self);
+                return;
+            }
+        }
+
+        expr_stmt.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call_expr: &CallExpr) {
+        if let Some(source) = dynamic_import_source(call_expr) {
+            self.dynamic_imports.push(DynamicImport {
+                source,
+                side_effect_only: false,
+                env_gated: false,
+                start_pos: call_expr.span.lo().0 as usize,
+            });
+        }
+
+        if let Some(pattern) = import_meta_glob_pattern(call_expr) {
+            self.import_globs.push(pattern);
+        }
+
+        if is_non_literal_require(call_expr) {
+            self.dynamic_requires.push(call_expr.span.lo().0 as usize);
+        }
+
+        if self.commonjs {
+            if let Some(source) = literal_require_source(call_expr) {
+                self.requires.push(source);
+            }
+        }
+
+        call_expr.visit_children_with(self);
+    }
+
+    fn visit_new_expr(&mut self, new_expr: &NewExpr) {
+        if let Some(specifier) = import_meta_url_specifier(new_expr) {
+            self.url_references.push(specifier);
+        }
+
+        new_expr.visit_children_with(self);
+    }
+}
+
+fn dynamic_import_source(call_expr: &CallExpr) -> Option<String> {
+    if !matches!(call_expr.callee, Callee::Import(_)) {
+        return None;
+    }
+
+    match call_expr.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(source))) => Some(source.value.to_string()),
+        _ => None,
+    }
+}
+
+// Vite's `import.meta.glob('./pages/*.ts')` (and the older `globEager`) pulls in every
+// matching module, so a literal pattern is as good as an import of each match
+fn import_meta_glob_pattern(call_expr: &CallExpr) -> Option<String> {
+    let Callee::Expr(callee) = &call_expr.callee else {
+        return None;
+    };
+    let Expr::Member(member) = &**callee else {
+        return None;
+    };
+    if !is_import_meta(&member.obj)
+        || !(member.prop.is_ident_with("glob") || member.prop.is_ident_with("globEager"))
+    {
+        return None;
+    }
+
+    match call_expr.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(pattern))) => Some(pattern.value.to_string()),
+        _ => None,
+    }
+}
+
+fn is_import_meta(expr: &Expr) -> bool {
+    matches!(expr, Expr::MetaProp(meta_prop) if matches!(meta_prop.kind, MetaPropKind::ImportMeta))
+}
+
+// `new URL('./worker.ts', import.meta.url)` is how bundlers reference workers and assets,
+// so the literal path is a reference to that file
+fn import_meta_url_specifier(new_expr: &NewExpr) -> Option<String> {
+    if !matches!(&*new_expr.callee, Expr::Ident(ident) if &*ident.sym == "URL") {
+        return None;
+    }
+    let [specifier, base] = new_expr.args.as_deref()? else {
+        return None;
+    };
+    let Expr::Member(member) = &*base.expr else {
+        return None;
+    };
+    if !is_import_meta(&member.obj) || !member.prop.is_ident_with("url") {
+        return None;
+    }
+
+    match &*specifier.expr {
+        Expr::Lit(Lit::Str(specifier)) => Some(specifier.value.to_string()),
+        _ => None,
+    }
+}
+
+fn is_require_call(call_expr: &CallExpr) -> bool {
+    let Callee::Expr(callee) = &call_expr.callee else {
+        return false;
+    };
+    matches!(&**callee, Expr::Ident(ident) if &*ident.sym == "require")
+}
+
+fn literal_require_source(call_expr: &CallExpr) -> Option<String> {
+    if !is_require_call(call_expr) {
+        return None;
+    }
+
+    match call_expr.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(source))) => Some(source.value.to_string()),
+        _ => None,
+    }
+}
+
+// `require(someVar)` or `require(`./${x}`)` can't be resolved, but may still reach files
+// that would otherwise look dead
+fn is_non_literal_require(call_expr: &CallExpr) -> bool {
+    if !is_require_call(call_expr) {
+        return false;
+    }
+
+    match call_expr.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(_))) | None => false,
+        Some(Expr::Tpl(tpl)) => !tpl.exprs.is_empty(),
+        Some(_) => true,
+    }
+}
+
+// Collects every identifier that could refer to a module-level binding: values, types and JSX
+// tags alike. Names that are really keys or labels (`obj.foo`, `{ foo: 1 }`, `<div foo="" />`,
+// `interface I { foo: T }`, `enum E { foo }`, `#foo`, `foo: for (;;)`) never do, nor do names
+// that a function, block or catch clause binds locally. Import statements and re-exports from
+// other modules only name other modules' exports, so they are skipped too
+struct ReferencedIdents {
+    names: HashSet<String>,
+    // Names bound by each enclosing function, block, loop or catch clause
+    scopes: Vec<HashSet<String>>,
+}
+
+impl ReferencedIdents {
+    fn visit_in_scope<T: VisitWith<Self>>(&mut self, bindings: HashSet<String>, node: &T) {
+        self.scopes.push(bindings);
+        node.visit_children_with(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visit for ReferencedIdents {
+    fn visit_ident(&mut self, ident: &Ident) {
+        if !self.scopes.iter().any(|scope| scope.contains(&*ident.sym)) {
+            self.names.insert(ident.sym.to_string());
+        }
+    }
+
+    // A binding introduces a name rather than referring to one, but its type annotation refers
+    fn visit_binding_ident(&mut self, binding: &BindingIdent) {
+        binding.type_ann.visit_with(self);
+    }
+
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    // `export import helper = require('./helper')` uses the binding it declares
+    fn visit_ts_import_equals_decl(&mut self, import_equals: &TsImportEqualsDecl) {
+        if import_equals.is_export {
+            import_equals.id.visit_with(self);
+        }
+        import_equals.module_ref.visit_with(self);
+    }
+
+    fn visit_named_export(&mut self, named_export: &NamedExport) {
+        if named_export.src.is_none() {
+            named_export.visit_children_with(self);
+        }
+    }
+
+    fn visit_export_named_specifier(&mut self, specifier: &ExportNamedSpecifier) {
+        specifier.orig.visit_with(self);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        self.visit_in_scope(bound_names(&function.params), function);
+    }
+
+    fn visit_fn_expr(&mut self, fn_expr: &FnExpr) {
+        let bindings = fn_expr
+            .ident
+            .iter()
+            .map(|ident| ident.sym.to_string())
+            .collect();
+        self.visit_in_scope(bindings, fn_expr);
+    }
+
+    fn visit_arrow_expr(&mut self, arrow_expr: &ArrowExpr) {
+        self.visit_in_scope(bound_names(&arrow_expr.params), arrow_expr);
+    }
+
+    fn visit_constructor(&mut self, constructor: &Constructor) {
+        self.visit_in_scope(bound_names(&constructor.params), constructor);
+    }
+
+    fn visit_setter_prop(&mut self, setter: &SetterProp) {
+        self.visit_in_scope(bound_names(&setter.param), setter);
+    }
+
+    fn visit_catch_clause(&mut self, catch_clause: &CatchClause) {
+        self.visit_in_scope(bound_names(&catch_clause.param), catch_clause);
+    }
+
+    fn visit_block_stmt(&mut self, block: &BlockStmt) {
+        self.visit_in_scope(declared_names(&block.stmts), block);
+    }
+
+    fn visit_switch_stmt(&mut self, switch_stmt: &SwitchStmt) {
+        switch_stmt.discriminant.visit_with(self);
+
+        let bindings = declared_names(switch_stmt.cases.iter().flat_map(|case| &case.cons));
+        self.visit_in_scope(bindings, &switch_stmt.cases);
+    }
+
+    fn visit_for_stmt(&mut self, for_stmt: &ForStmt) {
+        self.visit_in_scope(bound_names(&for_stmt.init), for_stmt);
+    }
+
+    fn visit_for_in_stmt(&mut self, for_in: &ForInStmt) {
+        self.visit_in_scope(for_head_names(&for_in.left), for_in);
+    }
+
+    fn visit_for_of_stmt(&mut self, for_of: &ForOfStmt) {
+        self.visit_in_scope(for_head_names(&for_of.left), for_of);
+    }
+
+    fn visit_member_prop(&mut self, member_prop: &MemberProp) {
+        if let MemberProp::Computed(computed) = member_prop {
+            computed.visit_with(self);
+        }
+    }
+
+    fn visit_super_prop(&mut self, super_prop: &SuperProp) {
+        if let SuperProp::Computed(computed) = super_prop {
+            computed.visit_with(self);
+        }
+    }
+
+    fn visit_prop_name(&mut self, prop_name: &PropName) {
+        if let PropName::Computed(computed) = prop_name {
+            computed.visit_with(self);
+        }
+    }
+
+    fn visit_private_name(&mut self, _: &PrivateName) {}
+
+    fn visit_ts_property_signature(&mut self, signature: &TsPropertySignature) {
+        if signature.computed {
+            signature.key.visit_with(self);
+        }
+        signature.init.visit_with(self);
+        signature.params.visit_with(self);
+        signature.type_ann.visit_with(self);
+        signature.type_params.visit_with(self);
+    }
+
+    fn visit_ts_method_signature(&mut self, signature: &TsMethodSignature) {
+        if signature.computed {
+            signature.key.visit_with(self);
+        }
+        signature.params.visit_with(self);
+        signature.type_ann.visit_with(self);
+        signature.type_params.visit_with(self);
+    }
+
+    fn visit_ts_getter_signature(&mut self, signature: &TsGetterSignature) {
+        if signature.computed {
+            signature.key.visit_with(self);
+        }
+        signature.type_ann.visit_with(self);
+    }
+
+    fn visit_ts_setter_signature(&mut self, signature: &TsSetterSignature) {
+        if signature.computed {
+            signature.key.visit_with(self);
+        }
+        signature.param.visit_with(self);
+    }
+
+    fn visit_ts_enum_member_id(&mut self, _: &TsEnumMemberId) {}
+
+    fn visit_ts_type_param(&mut self, type_param: &TsTypeParam) {
+        type_param.constraint.visit_with(self);
+        type_param.default.visit_with(self);
+    }
+
+    fn visit_ts_qualified_name(&mut self, qualified_name: &TsQualifiedName) {
+        qualified_name.left.visit_with(self);
+    }
+
+    fn visit_labeled_stmt(&mut self, labeled_stmt: &LabeledStmt) {
+        labeled_stmt.body.visit_with(self);
+    }
+
+    fn visit_break_stmt(&mut self, _: &BreakStmt) {}
+
+    fn visit_continue_stmt(&mut self, _: &ContinueStmt) {}
+
+    fn visit_jsx_member_expr(&mut self, member_expr: &JSXMemberExpr) {
+        member_expr.obj.visit_with(self);
+    }
+
+    fn visit_jsx_attr_name(&mut self, _: &JSXAttrName) {}
+}
+
+// Collects the names bound by patterns such as parameters and variable declarators
+struct BindingNames {
+    names: HashSet<String>,
+}
+
+impl Visit for BindingNames {
+    fn visit_binding_ident(&mut self, binding: &BindingIdent) {
+        self.names.insert(binding.id.sym.to_string());
+    }
+
+    // Default values and computed keys are expressions, not bindings
+    fn visit_expr(&mut self, _: &Expr) {}
+}
+
+fn bound_names<T: VisitWith<BindingNames>>(node: &T) -> HashSet<String> {
+    let mut bindings = BindingNames {
+        names: HashSet::new(),
+    };
+    node.visit_with(&mut bindings);
+    bindings.names
+}
+
+// `for (const x of xs)` binds `x`, while `for (x of xs)` assigns to an outer `x`
+fn for_head_names(for_head: &ForHead) -> HashSet<String> {
+    match for_head {
+        ForHead::VarDecl(var_decl) => bound_names(var_decl),
+        ForHead::UsingDecl(using_decl) => bound_names(using_decl),
+        ForHead::Pat(_) => HashSet::new(),
+    }
+}
+
+// The names a block's own statements declare, which shadow outer bindings throughout the block
+fn declared_names<'a>(stmts: impl IntoIterator<Item = &'a Stmt>) -> HashSet<String> {
+    let mut names = HashSet::new();
+
+    for stmt in stmts {
+        let Stmt::Decl(decl) = stmt else {
+            continue;
+        };
+        match decl {
+            Decl::Fn(fn_decl) => {
+                names.insert(fn_decl.ident.sym.to_string());
+            }
+            Decl::Class(class_decl) => {
+                names.insert(class_decl.ident.sym.to_string());
+            }
+            Decl::TsInterface(interface) => {
+                names.insert(interface.id.sym.to_string());
+            }
+            Decl::TsTypeAlias(type_alias) => {
+                names.insert(type_alias.id.sym.to_string());
+            }
+            Decl::TsEnum(ts_enum) => {
+                names.insert(ts_enum.id.sym.to_string());
+            }
+            Decl::Var(var_decl) => names.extend(bound_names(var_decl)),
+            Decl::Using(using_decl) => names.extend(bound_names(using_decl)),
+            Decl::TsModule(_) => {}
+        }
+    }
+
+    names
+}
+
+// Records the dynamic imports that only run when a condition on the environment holds, e.g.
+// `if (process.env.FEATURE_X) { import('./feature') }` or `import.meta.env.DEV && import('./dev')`
+struct EnvGatedImports {
+    env_conditions: usize,
+    start_positions: HashSet<usize>,
+}
+
+impl EnvGatedImports {
+    fn visit_gated<T: VisitWith<Self>>(&mut self, test: &Expr, branches: &[&T]) {
+        test.visit_with(self);
+
+        let gated = references_env(test);
+        if gated {
+            self.env_conditions += 1;
+        }
+        for branch in branches {
+            branch.visit_with(self);
+        }
+        if gated {
+            self.env_conditions -= 1;
+        }
+    }
+}
+
+impl Visit for EnvGatedImports {
+    fn visit_if_stmt(&mut self, if_stmt: &IfStmt) {
+        let mut branches = vec![&*if_stmt.cons];
+        if let Some(alt) = &if_stmt.alt {
+            branches.push(&**alt);
+        }
+        self.visit_gated(&if_stmt.test, &branches);
+    }
+
+    fn visit_cond_expr(&mut self, cond_expr: &CondExpr) {
+        self.visit_gated(&cond_expr.test, &[&*cond_expr.cons, &*cond_expr.alt]);
+    }
+
+    fn visit_switch_stmt(&mut self, switch_stmt: &SwitchStmt) {
+        self.visit_gated(&switch_stmt.discriminant, &[&switch_stmt.cases]);
+    }
+
+    fn visit_bin_expr(&mut self, bin_expr: &BinExpr) {
+        if matches!(
+            bin_expr.op,
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr | BinaryOp::NullishCoalescing
+        ) {
+            self.visit_gated(&bin_expr.left, &[&*bin_expr.right]);
+        } else {
+            bin_expr.visit_children_with(self);
+        }
+    }
+
+    fn visit_call_expr(&mut self, call_expr: &CallExpr) {
+        if self.env_conditions > 0 && dynamic_import_source(call_expr).is_some() {
+            self.start_positions.insert(call_expr.span.lo().0 as usize);
+        }
+
+        call_expr.visit_children_with(self);
+    }
+}
+
+struct EnvReference {
+    found: bool,
+}
+
+impl Visit for EnvReference {
+    fn visit_member_expr(&mut self, member_expr: &MemberExpr) {
+        let is_process =
+            matches!(&*member_expr.obj, Expr::Ident(ident) if &*ident.sym == "process");
+        if (is_process || is_import_meta(&member_expr.obj)) && member_expr.prop.is_ident_with("env")
+        {
+            self.found = true;
+        }
+
+        member_expr.visit_children_with(self);
+    }
+}
+
+fn references_env(expr: &Expr) -> bool {
+    let mut reference = EnvReference { found: false };
+    expr.visit_with(&mut reference);
+    reference.found
+}
+
+// Looks for anything that runs at module evaluation time. Function bodies, methods and instance
+// fields only run once called or instantiated, so they are not descended into
+struct SideEffectFinder {
+    found: bool,
+}
+
+impl SideEffectFinder {
+    fn visit_function_decorators(&mut self, function: &Function) {
+        function.decorators.visit_with(self);
+        for param in &function.params {
+            param.decorators.visit_with(self);
+        }
+    }
+}
+
+impl Visit for SideEffectFinder {
+    fn visit_call_expr(&mut self, _: &CallExpr) {
+        self.found = true;
+    }
+
+    fn visit_new_expr(&mut self, _: &NewExpr) {
+        self.found = true;
+    }
+
+    fn visit_tagged_tpl(&mut self, _: &TaggedTpl) {
+        self.found = true;
+    }
+
+    // Writes may reach outside the module, e.g. `globalThis.foo = 1` or `counter++`
+    fn visit_assign_expr(&mut self, _: &AssignExpr) {
+        self.found = true;
+    }
+
+    fn visit_update_expr(&mut self, _: &UpdateExpr) {
+        self.found = true;
+    }
+
+    fn visit_unary_expr(&mut self, unary_expr: &UnaryExpr) {
+        if unary_expr.op == UnaryOp::Delete {
+            self.found = true;
+        } else {
+            unary_expr.visit_children_with(self);
+        }
+    }
+
+    fn visit_function(&mut self, _: &Function) {}
+
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+
+    // Accessor bodies only run when the property is read or written
+    fn visit_getter_prop(&mut self, getter: &GetterProp) {
+        getter.key.visit_with(self);
+    }
+
+    fn visit_setter_prop(&mut self, setter: &SetterProp) {
+        setter.key.visit_with(self);
+    }
+
+    // Decorators, `extends`, computed keys, static fields and static blocks all run as soon as
+    // the class is defined
+    fn visit_class(&mut self, class: &Class) {
+        class.decorators.visit_with(self);
+        class.super_class.visit_with(self);
+
+        for member in &class.body {
+            match member {
+                ClassMember::Constructor(constructor) => {
+                    constructor.key.visit_with(self);
+                    for param in &constructor.params {
+                        match param {
+                            ParamOrTsParamProp::Param(param) => param.decorators.visit_with(self),
+                            ParamOrTsParamProp::TsParamProp(param) => {
+                                param.decorators.visit_with(self)
+                            }
+                        }
+                    }
+                }
+                ClassMember::Method(method) => {
+                    method.key.visit_with(self);
+                    self.visit_function_decorators(&method.function);
+                }
+                ClassMember::PrivateMethod(method) => {
+                    self.visit_function_decorators(&method.function)
+                }
+                ClassMember::ClassProp(prop) => {
+                    prop.key.visit_with(self);
+                    prop.decorators.visit_with(self);
+                    if prop.is_static {
+                        prop.value.visit_with(self);
+                    }
+                }
+                ClassMember::PrivateProp(prop) => {
+                    prop.decorators.visit_with(self);
+                    if prop.is_static {
+                        prop.value.visit_with(self);
+                    }
+                }
+                ClassMember::AutoAccessor(accessor) => {
+                    accessor.key.visit_with(self);
+                    accessor.decorators.visit_with(self);
+                    if accessor.is_static {
+                        accessor.value.visit_with(self);
+                    }
+                }
+                ClassMember::StaticBlock(static_block) => static_block.body.visit_with(self),
+                ClassMember::TsIndexSignature(_) | ClassMember::Empty(_) => {}
+            }
+        }
+    }
+
+    fn visit_decorator(&mut self, _: &Decorator) {
+        self.found = true;
+    }
+}
+
+fn has_possible_side_effects(module: &Module) -> bool {
+    let mut finder = SideEffectFinder { found: false };
+
+    for item in &module.body {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(decl)) => decl.visit_with(&mut finder),
+            ModuleItem::Stmt(Stmt::Empty(_)) => {}
+            // Directives like 'use strict' or 'use client' are plain string statements
+            ModuleItem::Stmt(Stmt::Expr(expr_stmt))
+                if matches!(&*expr_stmt.expr, Expr::Lit(Lit::Str(_))) => {}
+            // Top-level expression statements, loops, conditionals and the like
+            ModuleItem::Stmt(_) => return true,
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export_decl)) => {
+                export_decl.decl.visit_with(&mut finder)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export_default)) => {
+                export_default.expr.visit_with(&mut finder)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export_default)) => {
+                export_default.decl.visit_with(&mut finder)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::TsExportAssignment(export_assignment)) => {
+                export_assignment.expr.visit_with(&mut finder)
+            }
+            ModuleItem::ModuleDecl(_) => {}
+        }
+    }
+
+    finder.found
+}
+
+fn module_export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(string) => string.value.to_string(),
+    }
+}
+
+pub fn parse_module(input: &str) -> ModuleInfo {
+    parse_module_as(
+        SourceType {
+            tsx: false,
+            commonjs: false,
+        },
+        DEFAULT_GENERATED_MARKERS,
+        input,
+    )
+}
+
+#[derive(Clone, Copy)]
+pub struct SourceType {
+    pub tsx: bool,
+    pub commonjs: bool,
+}
+
+impl SourceType {
+    // `.cts`/`.cjs` are always CommonJS and `.mts`/`.mjs` always ESM, whatever package.json says;
+    // everything else is treated as ESM.
+    pub fn from_path(path: &Path) -> SourceType {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+        SourceType {
+            tsx: matches!(extension, "tsx" | "jsx"),
+            commonjs: matches!(extension, "cts" | "cjs"),
+        }
+    }
+
+    // Lets build setups with compound extensions (`.web.js`, `.server.ts`) say how those files
+    // should be parsed. The longest matching suffix wins, falling back to `from_path`.
+    pub fn from_path_with_overrides(path: &Path, overrides: &[(&str, SourceType)]) -> SourceType {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+
+        overrides
+            .iter()
+            .filter(|(suffix, _)| file_name.ends_with(suffix))
+            .max_by_key(|(suffix, _)| suffix.len())
+            .map(|(_, source_type)| *source_type)
+            .unwrap_or_else(|| SourceType::from_path(path))
+    }
+}
+
+// Like `parse_module`, but lets the file extension pick the parser and module system
+pub fn parse_module_at(path: &Path, input: &str) -> ModuleInfo {
+    parse_module_with_config(path, &ParseConfig::default(), input)
+}
+
+pub struct ParseConfig<'a> {
+    // Consulted before the file extension, see `SourceType::from_path_with_overrides`
+    pub overrides: &'a [(&'a str, SourceType)],
+    // Header comment markers that flag a file as generated
+    pub generated_markers: &'a [&'a str],
+}
+
+impl Default for ParseConfig<'_> {
+    fn default() -> Self {
+        ParseConfig {
+            overrides: &[],
+            generated_markers: DEFAULT_GENERATED_MARKERS,
+        }
+    }
+}
+
+// Like `parse_module_at`, but with configured suffix overrides and generated-file markers
+pub fn parse_module_with_config(path: &Path, config: &ParseConfig, input: &str) -> ModuleInfo {
+    parse_module_as(
+        SourceType::from_path_with_overrides(path, config.overrides),
+        config.generated_markers,
+        input,
+    )
+}
+
+pub fn parse_module_as(
+    source_type: SourceType,
+    generated_markers: &[&str],
+    input: &str,
+) -> ModuleInfo {
+    let comments = SingleThreadedComments::default();
+    let parser = create_parser_for_input(input, source_type.tsx, Some(&comments));
+
+    let mut info = analyze_module(parser, source_type.commonjs);
+    record_ignore_directives(&mut info, &comments, input);
+    info.generated = is_generated(&comments, input, generated_markers);
+    info
+}
+
+pub const DEFAULT_GENERATED_MARKERS: &[&str] = &["@generated", "@auto-generated", "DO NOT EDIT"];
+
+// Code generators put their marker in a header comment, so only comments starting in the first
+// few lines are checked; the same text in code or a string literal doesn't count
+const GENERATED_MARKER_LINES: usize = 5;
+
+fn is_generated(comments: &SingleThreadedComments, input: &str, markers: &[&str]) -> bool {
+    let (leading, trailing) = comments.borrow_all();
+
+    leading
+        .values()
+        .chain(trailing.values())
+        .flatten()
+        .filter(|comment| line_at(input, comment.span.lo) <= GENERATED_MARKER_LINES)
+        .any(|comment| markers.iter().any(|marker| comment.text.contains(marker)))
+}
+
+// 1-based line of a byte position in `input`
+fn line_at(input: &str, pos: BytePos) -> usize {
+    input[..pos.0 as usize].matches('\n').count() + 1
+}
+
+fn analyze_module(mut parser: Parser<Lexer<'_>>, commonjs: bool) -> ModuleInfo {
+    let mut info = ModuleInfo {
+        import_bindings: Vec::new(),
+        reexports: Vec::new(),
+        wildcard_reexports: Vec::new(),
+        namespace_reexports: Vec::new(),
+        dynamic_imports: Vec::new(),
+        import_globs: Vec::new(),
+        url_references: Vec::new(),
+        dynamic_requires: Vec::new(),
+        requires: Vec::new(),
+        has_side_effects: false,
+        commonjs,
+        ignored_file: false,
+        ignored_lines: Vec::new(),
+        generated: false,
+        parse_error: None,
+    };
+
+    match parser.parse_module() {
+        Ok(module) => {
+            module.visit_with(&mut info);
+            info.has_side_effects = has_possible_side_effects(&module);
+
+            let mut referenced = ReferencedIdents {
+                names: HashSet::new(),
+                scopes: Vec::new(),
+            };
+            module.visit_with(&mut referenced);
+            for binding in &mut info.import_bindings {
+                binding.referenced = referenced.names.contains(&binding.local);
+            }
+
+            let mut env_gated = EnvGatedImports {
+                env_conditions: 0,
+                start_positions: HashSet::new(),
+            };
+            module.visit_with(&mut env_gated);
+            for dynamic_import in &mut info.dynamic_imports {
+                dynamic_import.env_gated = env_gated
+                    .start_positions
+                    .contains(&dynamic_import.start_pos);
+            }
+
+            info
+        }
+        Err(e) => {
+            info.has_side_effects = true;
+            info.parse_error = Some(e.kind().msg().to_string());
+            info
+        }
+    }
+}
+
+fn record_ignore_directives(info: &mut ModuleInfo, comments: &SingleThreadedComments, input: &str) {
+    let (leading, trailing) = comments.borrow_all();
+
+    for comment in leading.values().chain(trailing.values()).flatten() {
+        // JSDoc-style comments keep their leading `*`, and a reason may follow the directive
+        let directive = comment
+            .text
+            .trim_start_matches(|c: char| c == '*' || c.is_whitespace())
+            .split_whitespace()
+            .next();
+        match directive.unwrap_or_default() {
+            "unused-buddy-ignore-file" => info.ignored_file = true,
+            "unused-buddy-ignore-next-line" => {
+                info.ignored_lines.push(line_at(input, comment.span.hi) + 1);
+            }
+            _ => {}
+        }
+    }
+
+    info.ignored_lines.sort_unstable();
+    info.ignored_lines.dedup();
+}
+
 pub fn find_functions(input: &str) -> usize {
-    let mut parser = create_parser_for_input(input);
+    let mut parser = create_parser_for_input(input, false, None);
 
     let mut functions = ExportedFunctions { functions: Vec::new() };
 
     match parser.parse_module() {
         Ok(module) => {
-            module.visit_with(&mut counter);
-            functions.functions
+            module.visit_with(&mut functions);
+            functions.functions.len()
         }
         Err(e) => {
             eprintln!("Error parsing input: {:?}", e);
@@ -42,25 +1040,34 @@ pub fn find_functions(input: &str) -> usize {
     }
 }
 
-fn create_parser_for_input(input: &str) -> Parser<Lexer<'_>> {
+fn create_parser_for_input<'a>(
+    input: &'a str,
+    tsx: bool,
+    comments: Option<&'a dyn Comments>,
+) -> Parser<Lexer<'a>> {
     let lexer = Lexer::new(
         Syntax::Typescript(TsConfig {
-            tsx: false,
-            decorators: false,
+            tsx,
+            decorators: true,
             dts: false,
             no_early_errors: false,
             disallow_ambiguous_jsx_like: false,
         }),
         swc_ecma_ast::EsVersion::Es2022,
         StringInput::new(input, BytePos(0), BytePos(input.len() as u32)),
-        None,
+        comments,
     );
     Parser::new_from(lexer)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::find_functions;
+    use std::path::Path;
+
+    use crate::{
+        find_functions, parse_module, parse_module_at, parse_module_with_config, ParseConfig,
+        SourceType,
+    };
 
     #[test]
     fn it_finds_exported_functions() {
@@ -79,4 +1086,469 @@ mod tests {
         // Expecting 3 exported functions: exportedFunc1, exportedFunc2, exportedFunc3
         assert_eq!(count, 3, "The count of exported functions should be 3.");
     }
+
+    #[test]
+    fn it_records_source_name_of_renamed_reexports() {
+        let ts_code = r#"
+            export { internal as publicName } from './a';
+            export { other } from './b';
+        "#;
+
+        let info = parse_module(ts_code);
+
+        let reexports: Vec<(&str, &str)> = info
+            .reexports
+            .iter()
+            .map(|r| (r.source.as_str(), r.name.as_str()))
+            .collect();
+
+        // The renamed re-export consumes `internal` from './a', not `publicName`
+        assert_eq!(reexports, vec![("./a", "internal"), ("./b", "other")]);
+    }
+
+    #[test]
+    fn it_records_import_meta_glob_patterns() {
+        let ts_code = r#"
+            const pages = import.meta.glob('./pages/*.ts');
+            const eager = import.meta.globEager('./widgets/*.ts');
+            const dynamic = import.meta.glob(pattern);
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // Only literal patterns can be expanded against the collected files
+        assert_eq!(info.import_globs, vec!["./pages/*.ts", "./widgets/*.ts"]);
+    }
+
+    #[test]
+    fn it_records_non_literal_requires() {
+        let ts_code = r#"
+            const a = require('./a');
+            const b = require(`./b`);
+            const c = require(variable);
+            const d = require(`./locales/${lang}`);
+            const e = require();
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // Only `require(variable)` and the interpolated template can't be resolved; a bare
+        // `require()` references nothing at all
+        assert_eq!(info.dynamic_requires.len(), 2);
+    }
+
+    #[test]
+    fn it_excludes_default_from_wildcard_reexports() {
+        let ts_code = r#"
+            export * from './a';
+            export { default } from './b';
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // `export *` passes through named exports only, never the default
+        assert!(info.reexports_from("./a", "helper"));
+        assert!(!info.reexports_from("./a", "default"));
+        assert!(info.reexports_from("./b", "default"));
+    }
+
+    #[test]
+    fn it_ignores_multi_line_literals_when_detecting_side_effects() {
+        let ts_code = r#"
+            export const config = {
+                name: 'app',
+                nested: { retries: 3 },
+            };
+            export const query = `
+                SELECT *
+                FROM users
+            `;
+            export function init() {
+                window.addEventListener('load', start);
+            }
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // Declarations spanning several lines don't run anything on import
+        assert!(!info.has_side_effects);
+    }
+
+    #[test]
+    fn it_detects_side_effects_in_class_definitions() {
+        let side_effect_code = [
+            "export class Config { static defaults = loadDefaults(); }",
+            "export class Registry { static { register(Registry); } }",
+            "export class Button extends withTheme(Base) {}",
+            "@injectable() export class Service {}",
+            "export class Store { @observable items = []; }",
+            "export default class extends mixin(Base) {}",
+            "export = createClient();",
+        ];
+        for ts_code in side_effect_code {
+            assert!(parse_module(ts_code).has_side_effects, "{ts_code}");
+        }
+
+        let ts_code = r#"
+            export class Counter {
+                count = initialCount();
+                #step = defaultStep();
+                constructor() { register(this); }
+                increment() { this.count += compute(this.#step); }
+            }
+        "#;
+
+        // Instance fields, constructors and methods only run once the class is used
+        assert!(!parse_module(ts_code).has_side_effects);
+    }
+
+    #[test]
+    fn it_ignores_directive_prologues_when_detecting_side_effects() {
+        let ts_code = r#"
+            'use strict';
+            'use client';
+            export const Button = () => null;
+        "#;
+
+        let info = parse_module(ts_code);
+
+        assert!(!info.has_side_effects);
+    }
+
+    #[test]
+    fn it_reports_parse_errors_conservatively() {
+        let info = parse_module("import { a } from './a';\nexport const = ;");
+
+        // A module that can't be analyzed must not look side-effect free
+        assert!(info.parse_error.is_some());
+        assert!(info.has_side_effects);
+        assert!(parse_module("export const a = 1;").parse_error.is_none());
+    }
+
+    #[test]
+    fn it_detects_top_level_side_effects() {
+        assert!(parse_module("window.addEventListener('load', start);").has_side_effects);
+        assert!(parse_module("export const client = createClient();").has_side_effects);
+        assert!(!parse_module("export const handler = () => createClient();").has_side_effects);
+        assert!(parse_module("export const x = (globalThis.foo = 1);").has_side_effects);
+        assert!(parse_module("export let n = counter++;").has_side_effects);
+        assert!(parse_module("export const removed = delete globalThis.foo;").has_side_effects);
+        // Accessor bodies run on access, not at definition
+        assert!(
+            !parse_module("export const obj = { get value() { return compute(); } };")
+                .has_side_effects
+        );
+        assert!(
+            !parse_module("export const obj = { set value(v) { store(v); } };").has_side_effects
+        );
+        assert!(
+            parse_module("export const obj = { get [key()]() { return 1; } };").has_side_effects
+        );
+    }
+
+    #[test]
+    fn it_treats_namespace_reexports_as_full_use() {
+        let ts_code = r#"
+            export * as utils from './mod';
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // The whole namespace is exposed, so every export of './mod' can be reached through it
+        assert_eq!(info.namespace_reexports, vec!["./mod"]);
+        assert!(info.reexports_from("./mod", "foo"));
+        assert!(info.reexports_from("./mod", "default"));
+    }
+
+    #[test]
+    fn it_records_new_url_references_relative_to_import_meta() {
+        let ts_code = r#"
+            const worker = new Worker(new URL('./worker.ts', import.meta.url));
+            const logo = new URL('./logo.svg', import.meta.url).href;
+            const api = new URL('/api', location.origin);
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // Only URLs resolved against `import.meta.url` point at files next to this module
+        assert_eq!(info.url_references, vec!["./worker.ts", "./logo.svg"]);
+    }
+
+    #[test]
+    fn it_forces_module_system_for_cts_and_mts() {
+        let cts_code = r#"
+            import config = require('./config');
+            const helper: (n: number) => number = require('./helper');
+            module.exports = { helper, port: config.port };
+        "#;
+        let mts_code = r#"
+            export { helper } from './helper.mjs';
+            export const double = (n: number): number => n * 2;
+        "#;
+
+        let cts = parse_module_at(Path::new("src/config.cts"), cts_code);
+        let mts = parse_module_at(Path::new("src/util.mts"), mts_code);
+
+        // `.cts` is CommonJS and `.mts` is ESM no matter what package.json `type` says
+        assert!(cts.commonjs);
+        assert_eq!(cts.requires, vec!["./helper"]);
+        let bindings: Vec<(&str, &str, bool)> = cts
+            .import_bindings
+            .iter()
+            .map(|b| (b.source.as_str(), b.local.as_str(), b.referenced))
+            .collect();
+        assert_eq!(bindings, vec![("./config", "config", true)]);
+        // The same code as ESM records no CommonJS edges
+        let as_esm = parse_module_at(Path::new("src/config.mts"), cts_code);
+        assert!(as_esm.requires.is_empty() && as_esm.import_bindings.is_empty());
+        assert!(!mts.commonjs);
+        assert!(mts.reexports_from("./helper.mjs", "helper"));
+    }
+
+    #[test]
+    fn it_distinguishes_side_effect_only_dynamic_imports() {
+        let ts_code = r#"
+            import('./polyfill');
+            await import('./setup');
+            void import('./analytics');
+            (import('./theme'));
+            const page = await import('./page');
+        "#;
+
+        let info = parse_module(ts_code);
+
+        let dynamic_imports: Vec<(&str, bool)> = info
+            .dynamic_imports
+            .iter()
+            .map(|d| (d.source.as_str(), d.side_effect_only))
+            .collect();
+
+        // A discarded result still runs the module, but none of its exports are consumed
+        assert_eq!(
+            dynamic_imports,
+            vec![
+                ("./polyfill", true),
+                ("./setup", true),
+                ("./analytics", true),
+                ("./theme", true),
+                ("./page", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_compound_extensions_with_overridden_source_types() {
+        let jsx_code = r#"
+            export { Text } from './Text';
+            export const App = () => <View><Text>Hi</Text></View>;
+        "#;
+        let overrides = [
+            (
+                ".js",
+                SourceType {
+                    tsx: false,
+                    commonjs: true,
+                },
+            ),
+            (
+                ".native.js",
+                SourceType {
+                    tsx: true,
+                    commonjs: false,
+                },
+            ),
+        ];
+
+        let plain = parse_module_at(Path::new("src/App.native.js"), jsx_code);
+        let overridden = parse_module_with_config(
+            Path::new("src/App.native.js"),
+            &ParseConfig {
+                overrides: &overrides,
+                ..ParseConfig::default()
+            },
+            jsx_code,
+        );
+
+        // JSX only parses once the override enables it, and the longer `.native.js` suffix wins
+        // over `.js`
+        assert!(plain.parse_error.is_some());
+        assert!(overridden.parse_error.is_none());
+        assert!(overridden.reexports_from("./Text", "Text"));
+        assert!(!overridden.commonjs);
+    }
+
+    #[test]
+    fn it_finds_unused_imports() {
+        let ts_code = r#"
+            import { a, b } from './x';
+            import type { Props, Unused } from './types';
+            import * as icons from './icons';
+            import Button from './Button';
+
+            export function render(props: Props) {
+                return <Button icon={icons.check} label={a(props)} />;
+            }
+
+            const config = { b: 1 };
+        "#;
+
+        let info = parse_module_at(Path::new("src/render.tsx"), ts_code);
+
+        let unused: Vec<&str> = info
+            .unused_imports()
+            .map(|binding| binding.local.as_str())
+            .collect();
+
+        // `b` only shows up as a property name; type-only, JSX and namespace uses all count
+        assert_eq!(unused, vec!["b", "Unused"]);
+    }
+
+    #[test]
+    fn it_ignores_keys_labels_and_shadowed_names_when_finding_unused_imports() {
+        let ts_code = r#"
+            import { a, b, c, d, e, f, g, h, used } from './x';
+
+            interface Shape { a: string; b(): void; get c(): number }
+            type Literal = { d: string };
+            enum Kind { e }
+
+            class Child extends Parent {
+                #f = 1;
+                run() { return super.g; }
+            }
+
+            h: for (;;) { break h; }
+
+            function shadowed(used) { return used; }
+            export const block = () => { const a = 1; return a; };
+            export { b } from './y';
+            export default used;
+        "#;
+
+        let info = parse_module(ts_code);
+
+        let unused: Vec<&str> = info
+            .unused_imports()
+            .map(|binding| binding.local.as_str())
+            .collect();
+
+        // `used` is shadowed inside `shadowed` but still referenced by the default export
+        assert_eq!(unused, vec!["a", "b", "c", "d", "e", "f", "g", "h"]);
+    }
+
+    #[test]
+    fn it_only_counts_referenced_imports_under_strict_usage() {
+        let ts_code = r#"
+            import { format, parse } from './dates';
+
+            export const today = () => format(new Date());
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // `parse` is imported but never referenced, so strict usage doesn't count it
+        assert_eq!(
+            info.imported_symbols(false),
+            vec![("./dates", "format"), ("./dates", "parse")]
+        );
+        assert_eq!(info.imported_symbols(true), vec![("./dates", "format")]);
+    }
+
+    #[test]
+    fn it_marks_env_gated_dynamic_imports() {
+        let ts_code = r#"
+            if (process.env.FEATURE_X) {
+                import('./feature');
+            }
+            const devtools = import.meta.env.DEV ? await import('./devtools') : null;
+            const analytics = process.env.ANALYTICS && import('./analytics');
+            const config = process.env.CONFIG ?? import('./config');
+            switch (process.env.MODE) {
+                case 'e2e':
+                    import('./e2e');
+            }
+            const page = await import('./page');
+        "#;
+
+        let info = parse_module(ts_code);
+
+        let dynamic_imports: Vec<(&str, bool)> = info
+            .dynamic_imports
+            .iter()
+            .map(|d| (d.source.as_str(), d.env_gated))
+            .collect();
+
+        // Whether the gated ones load depends on the environment, not on the code
+        assert_eq!(
+            dynamic_imports,
+            vec![
+                ("./feature", true),
+                ("./devtools", true),
+                ("./analytics", true),
+                ("./config", true),
+                ("./e2e", true),
+                ("./page", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_records_ignore_directives() {
+        let ts_code = r#"
+            // unused-buddy-ignore-next-line -- kept for plugins
+            export function publicApi() {}
+            export const message = '// unused-buddy-ignore-next-line';
+            export function internal() {}
+        "#;
+        let ignored_file_code = r#"
+            /* unused-buddy-ignore-file */
+            export function generated() {}
+        "#;
+        let jsdoc_code = r#"
+            /**
+             * unused-buddy-ignore-file
+             */
+            export function documented() {}
+        "#;
+
+        let info = parse_module(ts_code);
+        let ignored_file = parse_module(ignored_file_code);
+        let jsdoc = parse_module(jsdoc_code);
+
+        // Only the real comment counts, not the lookalike inside a string
+        assert_eq!(info.ignored_lines, vec![3]);
+        assert!(!info.ignored_file);
+        assert!(info.is_line_ignored(3));
+        assert!(!info.is_line_ignored(5));
+        assert!(ignored_file.ignored_file);
+        assert!(ignored_file.is_line_ignored(3));
+        assert!(jsdoc.ignored_file);
+    }
+
+    #[test]
+    fn it_flags_generated_files_by_header_marker() {
+        let ts_code = r#"// @generated by graphql-codegen
+            import { gql } from './client';
+            export const QUERY = gql`query { viewer { id } }`;
+        "#;
+
+        let info = parse_module(ts_code);
+
+        // Generated, but its imports are still recorded as uses of other modules
+        assert!(info.generated);
+        assert_eq!(info.imported_symbols(true), vec![("./client", "gql")]);
+
+        let late_marker = format!("{}// @generated\n", "export const a = 1;\n".repeat(10));
+        assert!(!parse_module(&late_marker).generated);
+        // Only comments count, not the same text in code
+        assert!(!parse_module("export const x = 'DO NOT EDIT this value';").generated);
+
+        let custom = ParseConfig {
+            generated_markers: &["eslint-disable"],
+            ..ParseConfig::default()
+        };
+        let eslint_header = "/* eslint-disable */\nexport const a = 1;\n";
+        assert!(parse_module_with_config(Path::new("src/a.ts"), &custom, eslint_header).generated);
+        assert!(!parse_module_with_config(Path::new("src/a.ts"), &custom, ts_code).generated);
+    }
 }